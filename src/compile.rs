@@ -1,5 +1,9 @@
 //! Build `OpenCL` programs from source
 
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
 use ocl::{Context, Device, Program};
 
 use crate::errors::{rewrap_ocl_result, ClgeomError};
@@ -23,26 +27,199 @@ macro_rules! get_source {
                 T1 = "float4",
                 T2 = "float4"
             )),
+            "normals" => Ok(include_str!("opencl/normals.c").to_owned()),
+            "transform" => Ok(include_str!("opencl/transform.c").to_owned()),
+            "raycast" => Ok(include_str!("opencl/raycast.c").to_owned()),
+            "triangle_bounds" => Ok(include_str!("opencl/triangle_bounds.c").to_owned()),
+            "triangle_areas" => Ok(include_str!("opencl/triangle_areas.c").to_owned()),
+            "triangle_volumes" => Ok(include_str!("opencl/triangle_volumes.c").to_owned()),
+            "triangle_weighted_centroids" => {
+                Ok(include_str!("opencl/triangle_weighted_centroids.c").to_owned())
+            }
+            "reduce_sum" => Ok(include_str!("opencl/reduce_sum.c").to_owned()),
+            "reduce_min" => Ok(include_str!("opencl/reduce_min.c").to_owned()),
+            "reduce_max" => Ok(include_str!("opencl/reduce_max.c").to_owned()),
             &_ => Err(ClgeomError::new(&format!("Unknown function: {}", $fn_name))),
         }
     }};
 }
 
+/// Options controlling how `OpenCL` programs are compiled.
+///
+/// Threaded through `ContextManager`/`ComputeContext` so callers can opt into on-disk binary
+/// caching without changing every `execute_kernel` call site.
+#[derive(Clone, Default)]
+pub struct BuildOptions {
+    /// Directory used to store compiled program binaries, keyed by a hash of their source and
+    /// target device/platform. `None` disables the on-disk cache.
+    pub cache_dir: Option<PathBuf>,
+
+    /// `OpenCL` compiler options string (e.g. `-cl-fast-relaxed-math`, `-cl-mad-enable`,
+    /// `-D NAME=value` preprocessor defines, include paths) forwarded to
+    /// `Program::builder().cmplr_opt(...)`. `None` uses the compiler defaults.
+    pub compiler_options: Option<String>,
+}
+
+/// Default cache directory for compiled program binaries.
+///
+/// Uses `$XDG_CACHE_HOME/libclgeom/program_cache` if set, falling back to the platform temp
+/// directory otherwise.
+pub fn default_cache_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("libclgeom").join("program_cache")
+}
+
 /// Return a compiled `ocl::Program` for the specified function
 ///
 /// # Arguments
 ///
 /// * `function` - name of function to retrieve program for
+/// * `context` - context to build the program for
 /// * `target` - device which will run the program
+/// * `options` - build options, including an optional on-disk binary cache
+/// * `registered` - source for kernels registered at runtime via `register_kernel`, consulted
+///   when `function` is not one of the built-in kernels
 ///
-pub fn get_program(function: &str, context: &Context, target: &Device) -> Result<Program, ClgeomError> {
-    rewrap_ocl_result(
-        Program::builder()
-            .source(get_source!(function)?)
-            .devices(target)
-            .build(context),
-        "building OpenCL program",
-    )
+pub fn get_program(
+    function: &str,
+    context: &Context,
+    target: &Device,
+    options: &BuildOptions,
+    registered: &HashMap<String, String>,
+) -> Result<Program, ClgeomError> {
+    let source = resolve_source(function, registered)?;
+
+    if let Some(cache_dir) = &options.cache_dir {
+        let key = rewrap_cache_key(&source, context, target, options)?;
+        if let Some(binary) = load_cached_binary(cache_dir, &key) {
+            if let Ok(program) = Program::builder()
+                .binaries(&[binary.as_slice()])
+                .devices(target)
+                .build(context)
+            {
+                return Ok(program);
+            }
+        }
+
+        let program = build_from_source(context, target, source, options)?;
+        if let Ok(binaries) = program.info(ocl::enums::ProgramInfo::Binaries) {
+            if let ocl::enums::ProgramInfoResult::Binaries(binaries) = binaries {
+                if let Some(binary) = binaries.into_iter().next() {
+                    // A cache-write failure (unwritable directory, full disk) shouldn't fail a
+                    // build that already succeeded in memory; just skip caching this once.
+                    let _ = store_cached_binary(cache_dir, &key, &binary);
+                }
+            }
+        }
+        return Ok(program);
+    }
+
+    build_from_source(context, target, source, options)
+}
+
+// Look up a function's source: the built-in kernel library first, falling back to kernels
+// registered at runtime via `register_kernel` so callers can extend the library without
+// recompiling the crate.
+fn resolve_source(function: &str, registered: &HashMap<String, String>) -> Result<String, ClgeomError> {
+    match get_source!(function) {
+        Ok(source) => Ok(source),
+        Err(builtin_err) => registered.get(function).cloned().ok_or(builtin_err),
+    }
+}
+
+// Build a `Program` from source, applying the compiler options string if one is set.
+fn build_from_source(
+    context: &Context,
+    target: &Device,
+    source: String,
+    options: &BuildOptions,
+) -> Result<Program, ClgeomError> {
+    let mut builder = Program::builder();
+    builder.source(source).devices(target);
+    if let Some(compiler_options) = &options.compiler_options {
+        builder.cmplr_opt(compiler_options.as_str());
+    }
+    rewrap_ocl_result(builder.build(context), "building OpenCL program")
+}
+
+/// Retrieve the `CL_PROGRAM_BUILD_LOG` for a program already built for `device`. Useful for
+/// inspecting compiler warnings after a successful build, not just on failure.
+pub fn get_build_log(program: &Program, device: &Device) -> Result<String, ClgeomError> {
+    match rewrap_ocl_result(
+        program.build_info(*device, ocl::enums::ProgramBuildInfo::BuildLog),
+        "reading OpenCL program build log",
+    )? {
+        ocl::enums::ProgramBuildInfoResult::BuildLog(log) => Ok(log),
+        _ => Ok(String::new()),
+    }
+}
+
+/// Retrieve the `CL_PROGRAM_BUILD_STATUS` for a program already built for `device`.
+pub fn get_build_status(program: &Program, device: &Device) -> Result<String, ClgeomError> {
+    match rewrap_ocl_result(
+        program.build_info(*device, ocl::enums::ProgramBuildInfo::BuildStatus),
+        "reading OpenCL program build status",
+    )? {
+        ocl::enums::ProgramBuildInfoResult::BuildStatus(status) => Ok(format!("{:?}", status)),
+        _ => Ok(String::new()),
+    }
+}
+
+// Compute a cache key from the program source plus the device/platform/driver/compiler options it
+// targets, so a source or option change, or a different device/platform/driver, naturally misses
+// the cache instead of loading a stale binary.
+fn rewrap_cache_key(
+    source: &str,
+    context: &Context,
+    target: &Device,
+    options: &BuildOptions,
+) -> Result<String, ClgeomError> {
+    let device_name = rewrap_ocl_result(target.name(), "getting device name")?;
+    let device_version = rewrap_ocl_result(target.version(), "getting device driver version")?;
+    let platform_name = match rewrap_ocl_result(context.platform(), "getting context platform")? {
+        Some(platform) => rewrap_ocl_result(platform.name(), "getting platform name")?,
+        None => String::new(),
+    };
+    let compiler_options = options.compiler_options.clone().unwrap_or_default();
+    Ok(cache_key(
+        &format!("{}{}", source, compiler_options),
+        &device_name,
+        &platform_name,
+        &format!("{}", device_version),
+    ))
+}
+
+// FNV-1a hash over the concatenation of the source text, device name, platform name and driver
+// version, formatted as a filename-safe hex string.
+fn cache_key(source: &str, device_name: &str, platform_name: &str, driver_version: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in source
+        .bytes()
+        .chain(device_name.bytes())
+        .chain(platform_name.bytes())
+        .chain(driver_version.bytes())
+    {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn cache_path(cache_dir: &std::path::Path, key: &str) -> PathBuf {
+    cache_dir.join(format!("{}.bin", key))
+}
+
+fn load_cached_binary(cache_dir: &std::path::Path, key: &str) -> Option<Vec<u8>> {
+    fs::read(cache_path(cache_dir, key)).ok()
+}
+
+fn store_cached_binary(cache_dir: &std::path::Path, key: &str, binary: &[u8]) -> Result<(), ClgeomError> {
+    fs::create_dir_all(cache_dir)
+        .map_err(|e| ClgeomError::new(&format!("Error creating program cache directory: {}", e)))?;
+    fs::write(cache_path(cache_dir, key), binary)
+        .map_err(|e| ClgeomError::new(&format!("Error writing cached program binary: {}", e)))
 }
 
 #[cfg(test)]
@@ -1,15 +1,42 @@
 //! Core ocl tools
 
+use std::collections::HashMap;
 use std::iter::Iterator;
+use std::path::PathBuf;
 
-use ocl::flags::{MemFlags, DEVICE_TYPE_GPU};
+use ocl::flags::{MemFlags, DEVICE_TYPE_ACCELERATOR, DEVICE_TYPE_ALL, DEVICE_TYPE_CPU, DEVICE_TYPE_GPU};
 use ocl::prm::Float4;
 use ocl::traits::OclPrm;
 use ocl::{Buffer, Context, Device, Kernel, Platform, Queue};
 
-use crate::compile::get_program;
+use crate::compile::{get_build_log, get_program, BuildOptions};
 use crate::errors::{rewrap_ocl_result, ClgeomError};
 
+/// Class of `OpenCL` device to enumerate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceType {
+    /// Graphics processing units.
+    Gpu,
+    /// Central processing units, e.g. software/CPU ICDs.
+    Cpu,
+    /// Dedicated accelerators (e.g. FPGAs, DSPs).
+    Accelerator,
+    /// Any device type.
+    All,
+}
+
+impl DeviceType {
+    // The `ocl` device type flags matching this filter.
+    fn to_ocl_flags(self) -> ocl::flags::DeviceType {
+        match self {
+            Self::Gpu => DEVICE_TYPE_GPU,
+            Self::Cpu => DEVICE_TYPE_CPU,
+            Self::Accelerator => DEVICE_TYPE_ACCELERATOR,
+            Self::All => DEVICE_TYPE_ALL,
+        }
+    }
+}
+
 /// Represents an `ocl::Device`. Only valid for the `ContextManager` which created it.
 pub struct DeviceInfo {
     /// Unique identifier among devices on a platform.
@@ -23,15 +50,31 @@ pub struct DeviceInfo {
 
     /// The name of the platform.
     pub platform_name: String,
+
+    /// The class of device (GPU, CPU, accelerator, ...).
+    pub device_type: DeviceType,
 }
 
 type BufferResult<T> = Result<Buffer<T>, ClgeomError>;
 
+// Owns the `OpenCL` resources backing a `ComputeContext` and drops them in a safe, deterministic
+// order. Rust drops struct fields top-to-bottom, so this ordering matters: queues reference both
+// the context and their device, so they must go first; the devices outlive the context built
+// from them, so the context goes before them. `build_options` (which holds the program cache directory)
+// is dropped alongside, before the `Context` it was used to build programs against.
+struct ComputeSession {
+    queues: Vec<Queue>,
+    build_options: BuildOptions,
+    context: Context,
+    devices: Vec<Device>,
+}
+
 /// Wraps a `ocl::ComputeContext`.
 pub struct ComputeContext {
-    /// The wrapped `ocl::Context`.
-    context: Context,
-    queue: Queue,
+    session: ComputeSession,
+
+    /// Kernel source registered at runtime via `register_kernel`, keyed by kernel name.
+    registered_kernels: HashMap<String, String>,
 }
 
 impl ComputeContext {
@@ -43,7 +86,7 @@ impl ComputeContext {
         };
         rewrap_ocl_result(
             Buffer::builder()
-                .context(&self.context)
+                .context(&self.session.context)
                 .copy_host_slice(data)
                 .flags(flags)
                 .len(data.len())
@@ -55,7 +98,7 @@ impl ComputeContext {
     pub fn create_empty_buffer<T: OclPrm>(&self, size: usize) -> BufferResult<T> {
         rewrap_ocl_result(
             Buffer::builder()
-                .context(&self.context)
+                .context(&self.session.context)
                 .flags(MemFlags::READ_WRITE)
                 .len(size)
                 .build(),
@@ -63,6 +106,9 @@ impl ComputeContext {
         )
     }
 
+    /// Run the named kernel over `data`. When the context spans several devices, the global work
+    /// range is split into contiguous chunks, one per device/queue, and run concurrently; this
+    /// call blocks until every chunk has completed.
     pub fn execute_kernel(
         &self,
         name: &str,
@@ -70,42 +116,136 @@ impl ComputeContext {
         args: Vec<ParamType>,
         size: usize,
     ) -> Result<(), ClgeomError> {
-        let devices = self.context.devices();
-        let device = match devices.get(0) {
-            Some(v) => v,
-            None => return Err(ClgeomError::new("Error getting device")),
-        };
-        let program = get_program(name, &self.context, device)?;
-        let mut kernel_builder = Kernel::builder();
-        kernel_builder.arg(data);
-        for arg in args {
-            match arg {
-                ParamType::Buffer(content) => {
-                    kernel_builder.arg(content);
-                }
-                ParamType::Value(content) => {
-                    kernel_builder.arg(content);
-                }
-            };
+        let chunk_count = self.session.queues.len();
+        if chunk_count == 0 {
+            return Err(ClgeomError::new("Error getting device"));
         }
-        let kernel = rewrap_ocl_result(
-            kernel_builder
-                .global_work_size(size)
-                .name(name)
-                .program(&program)
-                .queue(self.queue.clone())
-                .build(),
-            &format!("building kernel for function: {}", name),
-        )?;
-        // Safety: user is responsible for supplying appropriate kernel args
-        unsafe { rewrap_ocl_result(kernel.enq(), &format!("running kernel: {}", name)) }
+        let chunk_size = (size + chunk_count - 1) / chunk_count;
+
+        for (index, (queue, device)) in self
+            .session
+            .queues
+            .iter()
+            .zip(self.session.devices.iter())
+            .enumerate()
+        {
+            let offset = index * chunk_size;
+            if offset >= size {
+                continue;
+            }
+            let count = chunk_size.min(size - offset);
+            let program = get_program(
+                name,
+                &self.session.context,
+                device,
+                &self.session.build_options,
+                &self.registered_kernels,
+            )?;
+            let mut kernel_builder = Kernel::builder();
+            kernel_builder.arg(data);
+            for arg in &args {
+                match arg {
+                    ParamType::Buffer(content) => {
+                        kernel_builder.arg(*content);
+                    }
+                    ParamType::Value(content) => {
+                        kernel_builder.arg(*content);
+                    }
+                };
+            }
+            let kernel = rewrap_ocl_result(
+                kernel_builder
+                    .global_work_offset(offset)
+                    .global_work_size(count)
+                    .name(name)
+                    .program(&program)
+                    .queue(queue.clone())
+                    .build(),
+                &format!("building kernel for function: {}", name),
+            )?;
+            // Safety: user is responsible for supplying appropriate kernel args
+            unsafe { rewrap_ocl_result(kernel.enq(), &format!("running kernel: {}", name))? };
+        }
+
+        for queue in &self.session.queues {
+            rewrap_ocl_result(queue.finish(), "waiting for kernel completion")?;
+        }
+        Ok(())
     }
 
     pub fn read_buffer(&self, buffer: &Buffer<Float4>) -> Result<Vec<Float4>, ClgeomError>{
-        let mut result = vec![Float4::new(0.0, 0.0, 0.0, 0.0); buffer.len()];
-        rewrap_ocl_result(buffer.read(&mut result).queue(&self.queue).enq(), "reading result")?;
+        self.read_buffer_generic(buffer)
+    }
+
+    /// Read back the full contents of any `OclPrm` buffer, e.g. the `u32` index buffers used by
+    /// `IndexedTriangleMesh`.
+    pub fn read_buffer_generic<T: OclPrm + Default>(&self, buffer: &Buffer<T>) -> Result<Vec<T>, ClgeomError> {
+        let queue = self
+            .session
+            .queues
+            .get(0)
+            .ok_or_else(|| ClgeomError::new("Error getting device"))?;
+        let mut result = vec![T::default(); buffer.len()];
+        rewrap_ocl_result(buffer.read(&mut result).queue(queue).enq(), "reading result")?;
         Ok(result)
     }
+
+    /// Read back a single element of any `OclPrm` buffer, without transferring the rest of its
+    /// contents — e.g. the final slot of a buffer that has been folded down by repeated
+    /// reduction passes.
+    pub fn read_buffer_element<T: OclPrm + Default>(
+        &self,
+        buffer: &Buffer<T>,
+        index: usize,
+    ) -> Result<T, ClgeomError> {
+        let queue = self
+            .session
+            .queues
+            .get(0)
+            .ok_or_else(|| ClgeomError::new("Error getting device"))?;
+        let mut result = [T::default()];
+        rewrap_ocl_result(
+            buffer.read(&mut result[..]).queue(queue).offset(index).enq(),
+            "reading result",
+        )?;
+        Ok(result[0])
+    }
+
+    /// Replace the program build options (e.g. to enable or disable the on-disk binary cache).
+    pub fn set_build_options(&mut self, options: BuildOptions) {
+        self.session.build_options = options;
+    }
+
+    /// Set the `OpenCL` compiler options string applied when building programs (e.g.
+    /// `-cl-fast-relaxed-math`, `-D NAME=value`). Pass `None` to use the compiler defaults.
+    pub fn set_compiler_options(&mut self, options: Option<String>) {
+        self.session.build_options.compiler_options = options;
+    }
+
+    /// Retrieve the `OpenCL` build log for the named kernel's program, e.g. to inspect compiler
+    /// warnings after a successful build.
+    pub fn program_build_log(&self, name: &str) -> Result<String, ClgeomError> {
+        let device = self
+            .session
+            .devices
+            .get(0)
+            .ok_or_else(|| ClgeomError::new("Error getting device"))?;
+        let program = get_program(
+            name,
+            &self.session.context,
+            device,
+            &self.session.build_options,
+            &self.registered_kernels,
+        )?;
+        get_build_log(&program, device)
+    }
+
+    /// Register OpenCL C source for a kernel so it can be called by name through
+    /// `execute_kernel`, without recompiling the crate. Overwrites any previous registration
+    /// under the same name; built-in kernel names always take priority.
+    pub fn register_kernel(&mut self, name: &str, source: &str) {
+        self.registered_kernels.insert(name.to_owned(), source.to_owned());
+    }
 }
 
 pub enum ParamType<'a> {
@@ -137,11 +277,13 @@ pub struct ContextManager {
 }
 
 impl ContextManager {
-    /// Create a new `ContextManager` instance.
-    pub fn new() -> Result<Self, ClgeomError> {
+    /// Create a new `ContextManager` instance, enumerating only devices matching `device_type`.
+    pub fn new(device_type: DeviceType) -> Result<Self, ClgeomError> {
         let raw_platforms = Platform::list();
-        let platform_devices: Result<Vec<_>, _> =
-            raw_platforms.iter().map(|p| unwrap_devices(*p)).collect();
+        let platform_devices: Result<Vec<_>, _> = raw_platforms
+            .iter()
+            .map(|p| unwrap_devices(*p, device_type))
+            .collect();
         let ocl_platforms = match platform_devices {
             Ok(platforms) => platforms,
             Err(e) => return Err(e),
@@ -171,31 +313,88 @@ impl ContextManager {
     /// * `device` - device to create context with.
     ///
     pub fn create_context(&self, device: &DeviceInfo) -> Result<ComputeContext, ClgeomError> {
-        let mut builder = Context::builder();
-        let ocl_platform = self.ocl_platforms.get(device.platform_id).ok_or_else(|| {
-            ClgeomError::new(&format!("Error getting platform {}", device.platform_id))
-        })?;
-        let ocl_device = ocl_platform.devices.get(device.device_id).ok_or_else(|| {
-            ClgeomError::new(&format!(
-                "getting device {} for platform {}",
-                device.device_id, device.platform_id
-            ))
+        self.create_context_multi(&[device])
+    }
+
+    /// Create a `ComputeContext` spanning several devices on the same platform, with one command
+    /// queue per device. `execute_kernel` splits its work range across them.
+    ///
+    /// # Arguments
+    ///
+    /// * `devices` - devices to create the context with; must all share a platform.
+    ///
+    pub fn create_context_multi(&self, devices: &[&DeviceInfo]) -> Result<ComputeContext, ClgeomError> {
+        let first = devices
+            .first()
+            .ok_or_else(|| ClgeomError::new("No devices supplied"))?;
+        let platform_id = first.platform_id;
+        if devices.iter().any(|d| d.platform_id != platform_id) {
+            return Err(ClgeomError::new(
+                "All devices in a context must belong to the same platform",
+            ));
+        }
+        let ocl_platform = self.ocl_platforms.get(platform_id).ok_or_else(|| {
+            ClgeomError::new(&format!("Error getting platform {}", platform_id))
         })?;
+        let ocl_devices: Vec<Device> = devices
+            .iter()
+            .map(|d| {
+                ocl_platform.devices.get(d.device_id).copied().ok_or_else(|| {
+                    ClgeomError::new(&format!(
+                        "getting device {} for platform {}",
+                        d.device_id, platform_id
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut builder = Context::builder();
         builder.platform(ocl_platform.platform);
-        builder.devices(ocl_device);
+        builder.devices(ocl_devices.as_slice());
         let context = rewrap_ocl_result(builder.build(), "creating context")?;
-        let queue = rewrap_ocl_result(
-            Queue::new(&context, *ocl_device, None),
-            "creating command queue",
-        )?;
-        Ok(ComputeContext { context, queue })
+
+        let queues: Vec<Queue> = ocl_devices
+            .iter()
+            .map(|d| rewrap_ocl_result(Queue::new(&context, *d, None), "creating command queue"))
+            .collect::<Result<_, _>>()?;
+
+        Ok(ComputeContext {
+            session: ComputeSession {
+                queues,
+                build_options: BuildOptions::default(),
+                context,
+                devices: ocl_devices,
+            },
+            registered_kernels: HashMap::new(),
+        })
+    }
+
+    /// Create a `ComputeContext` with the indicated device, caching compiled program binaries
+    /// under `cache_dir` so repeated runs skip re-compiling unchanged kernels from source.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - device to create context with.
+    /// * `cache_dir` - directory to store compiled program binaries in.
+    ///
+    pub fn create_context_with_cache(
+        &self,
+        device: &DeviceInfo,
+        cache_dir: PathBuf,
+    ) -> Result<ComputeContext, ClgeomError> {
+        let mut context = self.create_context(device)?;
+        context.set_build_options(BuildOptions {
+            cache_dir: Some(cache_dir),
+            ..BuildOptions::default()
+        });
+        Ok(context)
     }
 }
 
-// Get a list of devices for the specified platform
-fn unwrap_devices(platform: Platform) -> Result<PlatformDevices, ClgeomError> {
+// Get a list of devices for the specified platform matching `device_type`
+fn unwrap_devices(platform: Platform, device_type: DeviceType) -> Result<PlatformDevices, ClgeomError> {
     let devices = rewrap_ocl_result(
-        Device::list(platform, Some(DEVICE_TYPE_GPU)),
+        Device::list(platform, Some(device_type.to_ocl_flags())),
         "listing devices",
     )?;
     Ok(PlatformDevices::new(platform, devices))
@@ -226,20 +425,44 @@ fn create_device_info(
     device: Device,
 ) -> Result<DeviceInfo, ClgeomError> {
     let device_name = rewrap_ocl_result(device.name(), "getting device name")?;
+    let device_type = detect_device_type(&device)?;
     Ok(DeviceInfo {
         device_id,
         device_name,
         platform_id,
         platform_name,
+        device_type,
     })
 }
 
+// Determine the actual class of a device by querying CL_DEVICE_TYPE, rather than trusting the
+// filter it was listed under (relevant when listing with `DeviceType::All`).
+fn detect_device_type(device: &Device) -> Result<DeviceType, ClgeomError> {
+    let info = rewrap_ocl_result(
+        device.info(ocl::enums::DeviceInfo::Type),
+        "getting device type",
+    )?;
+    let flags = match info {
+        ocl::enums::DeviceInfoResult::Type(flags) => flags,
+        _ => return Ok(DeviceType::All),
+    };
+    if flags.contains(DEVICE_TYPE_GPU) {
+        Ok(DeviceType::Gpu)
+    } else if flags.contains(DEVICE_TYPE_CPU) {
+        Ok(DeviceType::Cpu)
+    } else if flags.contains(DEVICE_TYPE_ACCELERATOR) {
+        Ok(DeviceType::Accelerator)
+    } else {
+        Ok(DeviceType::All)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     fn create_context_manager() -> ContextManager {
-        ContextManager::new().expect("Error creating ContextManager")
+        ContextManager::new(DeviceType::Gpu).expect("Error creating ContextManager")
     }
 
     #[test]
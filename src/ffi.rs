@@ -1,12 +1,12 @@
 //! C interface for libclgeom.h
 
 use std::boxed::Box;
-use std::ffi::{c_void, CString};
+use std::ffi::{c_void, CStr, CString};
 use std::mem::forget;
 use std::os::raw::c_char;
-use std::ptr::{null, write};
+use std::ptr::{null, null_mut, write};
 
-use crate::context::{ContextManager, DeviceInfo};
+use crate::context::{ComputeContext, ContextManager, DeviceInfo, DeviceType};
 use crate::errors::ClgeomError;
 
 
@@ -37,6 +37,29 @@ pub struct ClgeomDeviceInfo {
 
     /// The name of the device's platform
     platform_name: *const c_char,
+
+    /// The class of device: 0 = GPU, 1 = CPU, 2 = accelerator, 3 = other/unknown
+    device_type: u32,
+}
+
+// Encode a `DeviceType` as the `u32` reported in `ClgeomDeviceInfo::device_type`
+fn device_type_to_c(device_type: DeviceType) -> u32 {
+    match device_type {
+        DeviceType::Gpu => 0,
+        DeviceType::Cpu => 1,
+        DeviceType::Accelerator => 2,
+        DeviceType::All => 3,
+    }
+}
+
+// Decode the `u32` passed to `clgeom_create_context_manager_with_type` as a `DeviceType`
+fn device_type_from_c(device_type: u32) -> DeviceType {
+    match device_type {
+        0 => DeviceType::Gpu,
+        1 => DeviceType::Cpu,
+        2 => DeviceType::Accelerator,
+        _ => DeviceType::All,
+    }
 }
 
 /// Wraps a `ComputeContext` for use in C.
@@ -64,6 +87,15 @@ fn string_to_c_char(s: &str) -> Result<*mut c_char, ClgeomError> {
     }
 }
 
+// Convert a possibly-null `*const c_char` to an `Option<String>`
+unsafe fn c_char_to_option_string(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(s).to_string_lossy().into_owned())
+    }
+}
+
 // Create a `ClgeomDeviceInfo` instance
 fn create_c_device_info(device_info: &DeviceInfo) -> Result<ClgeomDeviceInfo, ClgeomError> {
     let device_name = match string_to_c_char(&device_info.device_name) {
@@ -79,12 +111,13 @@ fn create_c_device_info(device_info: &DeviceInfo) -> Result<ClgeomDeviceInfo, Cl
         device_name,
         platform_id: device_info.platform_id,
         platform_name,
+        device_type: device_type_to_c(device_info.device_type),
     })
 }
 
-// Create a `ClgeomContextManager`
-fn create_c_context_manager() -> Result<ClgeomContextManager, ClgeomError> {
-    let manager = match ContextManager::new() {
+// Create a `ClgeomContextManager`, enumerating only devices matching `device_type`
+fn create_c_context_manager(device_type: DeviceType) -> Result<ClgeomContextManager, ClgeomError> {
+    let manager = match ContextManager::new(device_type) {
         Ok(mgr) => mgr,
         Err(e) => return Err(e),
     };
@@ -115,8 +148,9 @@ fn create_c_context_manager() -> Result<ClgeomContextManager, ClgeomError> {
     Ok(c_manager)
 }
 
-/// Create a `ClgeomContextManager`. Generally only one should be created per session. The `ContextManager`
-/// should be deallocated using `clgeom_drop_context_manager()`. Returns a null pointer on error.
+/// Create a `ClgeomContextManager`, enumerating only GPU devices. Generally only one should be
+/// created per session. The `ContextManager` should be deallocated using
+/// `clgeom_drop_context_manager()`. Returns a null pointer on error.
 ///
 /// # Arguments
 ///
@@ -125,9 +159,26 @@ fn create_c_context_manager() -> Result<ClgeomContextManager, ClgeomError> {
 #[no_mangle]
 pub extern "C" fn clgeom_create_context_manager(
     error_code: *mut u32,
+) -> ClgeomContextManager {
+    clgeom_create_context_manager_with_type(device_type_to_c(DeviceType::Gpu), error_code)
+}
+
+/// Create a `ClgeomContextManager`, enumerating only devices of the given type. Generally only
+/// one should be created per session. The `ContextManager` should be deallocated using
+/// `clgeom_drop_context_manager()`. Returns a null pointer on error.
+///
+/// # Arguments
+///
+/// * `device_type` - 0 = GPU, 1 = CPU, 2 = accelerator, any other value = all device types.
+/// * `error_code`: Set to 0 if no errors are encountered, or a non-zero value to indicate an error.
+///
+#[no_mangle]
+pub extern "C" fn clgeom_create_context_manager_with_type(
+    device_type: u32,
+    error_code: *mut u32,
 ) -> ClgeomContextManager {
     let mut code = 0;
-    let result = create_c_context_manager().map_or_else(
+    let result = create_c_context_manager(device_type_from_c(device_type)).map_or_else(
         |_| {
             code = 999;
             ClgeomContextManager {
@@ -192,15 +243,212 @@ pub extern "C" fn clgeom_create_context(
         device_name: "".to_owned(),
         platform_id: c_dev_info.platform_id,
         platform_name: "".to_owned(),
+        device_type: device_type_from_c(c_dev_info.device_type),
     };
-    let result = ClgeomContext {
-        context: cast_boxed_raw(mgr.create_context(&dev_info)),
+    let mut code = 0;
+    let result = match mgr.create_context(&dev_info) {
+        Ok(ctx) => ClgeomContext {
+            context: cast_boxed_raw(ctx),
+        },
+        Err(_) => {
+            code = 999;
+            ClgeomContext { context: null() }
+        }
     };
     // Safety: safe as long as error_code is valid
-    unsafe { write(error_code, 0) };
+    unsafe { write(error_code, code) };
+    result
+}
+
+/// Create a `ClgeomContext` with the specified device and `OpenCL` compiler options (e.g.
+/// `-cl-fast-relaxed-math`, `-cl-mad-enable`, `-D NAME=value`). The context should be deallocated
+/// using `clgeom_drop_context`.
+///
+/// # Arguments
+///
+/// * `mgr_ptr` a pointer to the `ClgeomContextManager` to use to create the context.
+/// * `dev_ptr` a pointer to the `ClgeomDeviceInfo` to use.
+/// * `compiler_options` a NUL-terminated `OpenCL` compiler options string, or null for none.
+/// * `error_code`: Set to 0 if no errors are encountered, or a non-zero value to indicate an error.
+///
+#[no_mangle]
+pub extern "C" fn clgeom_create_context_with_options(
+    mgr_ptr: *const ClgeomContextManager,
+    dev_ptr: *const ClgeomDeviceInfo,
+    compiler_options: *const c_char,
+    error_code: *mut u32,
+) -> ClgeomContext {
+    let mut code = 0;
+    // Safety: safe as long as mgr_ptr is valid
+    let c_mgr = unsafe { &*mgr_ptr };
+    // Safety: safe as long as mgr_ptr is valid
+    let mgr = unsafe { &(*(c_mgr.manager.cast::<ContextManager>())) };
+    // Safety: safe as long as dev_ptr is valid
+    let c_dev_info = unsafe { &*dev_ptr };
+    let dev_info = DeviceInfo {
+        device_id: c_dev_info.device_id,
+        device_name: "".to_owned(),
+        platform_id: c_dev_info.platform_id,
+        platform_name: "".to_owned(),
+        device_type: device_type_from_c(c_dev_info.device_type),
+    };
+    // Safety: safe as long as compiler_options is null or a valid NUL-terminated string
+    let options = unsafe { c_char_to_option_string(compiler_options) };
+    let result = match mgr.create_context(&dev_info) {
+        Ok(mut ctx) => {
+            ctx.set_compiler_options(options);
+            ClgeomContext {
+                context: cast_boxed_raw(ctx),
+            }
+        }
+        Err(_) => {
+            code = 999;
+            ClgeomContext { context: null() }
+        }
+    };
+    // Safety: safe as long as error_code is valid
+    unsafe { write(error_code, code) };
+    result
+}
+
+/// Create a `ClgeomContext` spanning several devices on the same platform, with one command queue
+/// per device; `execute_kernel` splits its work range across them. The context should be
+/// deallocated using `clgeom_drop_context`.
+///
+/// # Arguments
+///
+/// * `mgr_ptr` a pointer to the `ClgeomContextManager` to use to create the context.
+/// * `dev_ptrs` pointer to an array of `n_devices` `ClgeomDeviceInfo` pointers; must share a platform.
+/// * `n_devices` number of entries in `dev_ptrs`.
+/// * `error_code`: Set to 0 if no errors are encountered, or a non-zero value to indicate an error.
+///
+#[no_mangle]
+pub extern "C" fn clgeom_create_context_multi(
+    mgr_ptr: *const ClgeomContextManager,
+    dev_ptrs: *const *const ClgeomDeviceInfo,
+    n_devices: usize,
+    error_code: *mut u32,
+) -> ClgeomContext {
+    let mut code = 0;
+    // Safety: safe as long as mgr_ptr is valid
+    let c_mgr = unsafe { &*mgr_ptr };
+    // Safety: safe as long as mgr_ptr is valid
+    let mgr = unsafe { &(*(c_mgr.manager.cast::<ContextManager>())) };
+    // Safety: safe as long as dev_ptrs points to n_devices valid ClgeomDeviceInfo pointers
+    let dev_infos: Vec<DeviceInfo> = unsafe { std::slice::from_raw_parts(dev_ptrs, n_devices) }
+        .iter()
+        .map(|p| {
+            // Safety: safe as long as each pointer in dev_ptrs is valid
+            let c_dev_info = unsafe { &**p };
+            DeviceInfo {
+                device_id: c_dev_info.device_id,
+                device_name: "".to_owned(),
+                platform_id: c_dev_info.platform_id,
+                platform_name: "".to_owned(),
+                device_type: device_type_from_c(c_dev_info.device_type),
+            }
+        })
+        .collect();
+    let dev_refs: Vec<&DeviceInfo> = dev_infos.iter().collect();
+    let result = match mgr.create_context_multi(&dev_refs) {
+        Ok(ctx) => ClgeomContext {
+            context: cast_boxed_raw(ctx),
+        },
+        Err(_) => {
+            code = 999;
+            ClgeomContext { context: null() }
+        }
+    };
+    // Safety: safe as long as error_code is valid
+    unsafe { write(error_code, code) };
     result
 }
 
+/// Retrieve the `OpenCL` build log for the named kernel's program on the given context. Useful
+/// for inspecting compiler warnings after a successful build, not just failures. The returned
+/// string is owned by the caller and must be freed with `clgeom_free_string`.
+///
+/// # Arguments
+///
+/// * `context_ptr` a pointer to the `ClgeomContext` to inspect.
+/// * `kernel_name` a NUL-terminated name of the kernel/function to report the build log for.
+/// * `error_code`: Set to 0 if no errors are encountered, or a non-zero value to indicate an error.
+///
+#[no_mangle]
+pub extern "C" fn clgeom_get_build_log(
+    context_ptr: *const ClgeomContext,
+    kernel_name: *const c_char,
+    error_code: *mut u32,
+) -> *mut c_char {
+    let mut code = 0;
+    // Safety: safe as long as context_ptr is valid
+    let c_context = unsafe { &*context_ptr };
+    // Safety: safe as long as c_context.context is valid
+    let context = unsafe { &*(c_context.context.cast::<ComputeContext>()) };
+    // Safety: safe as long as kernel_name is a valid NUL-terminated string
+    let name = unsafe { CStr::from_ptr(kernel_name) }.to_string_lossy().into_owned();
+    let result = context
+        .program_build_log(&name)
+        .and_then(|log| string_to_c_char(&log));
+    let ptr = result.unwrap_or_else(|_| {
+        code = 999;
+        null_mut()
+    });
+    // Safety: safe as long as error_code is valid
+    unsafe { write(error_code, code) };
+    ptr
+}
+
+/// Free a string previously returned by `clgeom_get_build_log`.
+///
+/// # Arguments
+///
+/// * `s` a pointer previously returned by `clgeom_get_build_log`.
+///
+#[no_mangle]
+pub extern "C" fn clgeom_free_string(s: *mut c_char) {
+    if s.is_null() {
+        return;
+    }
+    // Safety: safe as long as s was returned by a CString::into_raw() in this crate
+    unsafe {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Register `OpenCL` C source for a kernel on the given context, so it can be called by name
+/// through the same execution path as the built-in kernels.
+///
+/// # Arguments
+///
+/// * `context_ptr` a pointer to the `ClgeomContext` to register the kernel on.
+/// * `name` a NUL-terminated kernel name.
+/// * `source` a NUL-terminated string of `OpenCL` C source defining the kernel.
+/// * `error_code`: Set to 0 if no errors are encountered, or a non-zero value to indicate an error.
+///
+#[no_mangle]
+pub extern "C" fn clgeom_register_kernel(
+    context_ptr: *const ClgeomContext,
+    name: *const c_char,
+    source: *const c_char,
+    error_code: *mut u32,
+) {
+    // Safety: safe as long as context_ptr is valid
+    let c_context = unsafe { &*context_ptr };
+    // Safety: safe as long as c_context.context is valid and not aliased elsewhere
+    let context = unsafe { &mut *(c_context.context as *mut ComputeContext) };
+    // Safety: safe as long as name and source are valid NUL-terminated strings
+    let (name, source) = unsafe {
+        (
+            CStr::from_ptr(name).to_string_lossy().into_owned(),
+            CStr::from_ptr(source).to_string_lossy().into_owned(),
+        )
+    };
+    context.register_kernel(&name, &source);
+    // Safety: safe as long as error_code is valid
+    unsafe { write(error_code, 0) };
+}
+
 /// Drop the specified `ClgeomContext` and free its memory.
 ///
 /// # Arguments
@@ -210,9 +458,13 @@ pub extern "C" fn clgeom_create_context(
 ///
 #[no_mangle]
 pub extern "C" fn clgeom_drop_context(c_context: ClgeomContext, error_code: *mut u32) {
-    // Safety: safe as long as context_ptr is valid
+    // Safety: safe as long as context_ptr is valid and was boxed by one of the
+    // clgeom_create_context* functions, all of which box a `ComputeContext`. Dropping through
+    // `c_void` instead of the concrete type would skip `ComputeContext`'s destructor (and with it
+    // the queues-before-context-before-devices teardown ordering it implements) and deallocate
+    // with the wrong size/align.
     unsafe {
-        Box::from_raw(c_context.context as *mut c_void);
+        drop(Box::from_raw(c_context.context as *mut ComputeContext));
     }
     // Safety: safe as long as error_code is valid
     unsafe {
@@ -227,7 +479,7 @@ mod tests {
 
     #[test]
     fn get_c_context_manager() {
-        let mgr = create_c_context_manager().expect("Error creating ContextManager");
+        let mgr = create_c_context_manager(DeviceType::Gpu).expect("Error creating ContextManager");
         assert_ne!(mgr.n_devices, 0);
         println!("\nNumber of devices total: {}", mgr.n_devices);
     }
@@ -1,5 +1,8 @@
 //! Triangle mesh struct and associated operations
 
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+
 use ocl::prm::Float4;
 use ocl::Buffer;
 
@@ -29,6 +32,94 @@ impl<'a> TriangleMesh<'a> {
         })
     }
 
+    /// Build a `TriangleMesh` from a Wavefront OBJ stream. Indexed `f` faces are expanded into the
+    /// flat, duplicated-point triple layout the GPU buffer expects; polygonal faces with more than
+    /// 3 vertices are triangulated fan-style as `(v0, vi, vi+1)`.
+    pub fn from_obj_reader<R: Read>(
+        context: &'a ComputeContext,
+        reader: R,
+    ) -> Result<TriangleMesh<'a>, ClgeomError> {
+        let mut vertices: Vec<Float4> = Vec::new();
+        let mut triples: Vec<Float4> = Vec::new();
+
+        for line in BufReader::new(reader).lines() {
+            let line = line.map_err(|e| ClgeomError::new(&format!("Error reading OBJ stream: {}", e)))?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => vertices.push(parse_obj_vertex(tokens)?),
+                Some("f") => {
+                    let indices: Vec<usize> = tokens
+                        .map(|t| parse_obj_face_index(t, vertices.len()))
+                        .collect::<Result<_, _>>()?;
+                    if indices.len() < 3 {
+                        return Err(ClgeomError::new("OBJ 'f' record must reference at least 3 vertices"));
+                    }
+                    for i in 1..indices.len() - 1 {
+                        for &idx in &[indices[0], indices[i], indices[i + 1]] {
+                            let vertex = *vertices
+                                .get(idx)
+                                .ok_or_else(|| ClgeomError::new("OBJ face index out of range"))?;
+                            triples.push(vertex);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(TriangleMesh {
+            context,
+            data: context.create_buffer_from(&triples, true)?,
+        })
+    }
+
+    /// Triangulate a planar point set (each point placed at `z = 0`) via incremental Delaunay
+    /// insertion with edge flipping. Fails if the input is degenerate, e.g. all points collinear.
+    pub fn from_delaunay_2d(
+        context: &'a ComputeContext,
+        points: &[[f32; 2]],
+    ) -> Result<TriangleMesh<'a>, ClgeomError> {
+        if points.len() < 3 {
+            return Err(ClgeomError::new("Delaunay triangulation needs at least 3 points"));
+        }
+
+        let triangulation = Triangulation::build(points)
+            .ok_or_else(|| ClgeomError::new("Delaunay triangulation failed on degenerate or collinear input"))?;
+
+        let mut triples: Vec<Float4> = Vec::new();
+        for verts in triangulation.triangles.iter().flatten() {
+            if verts.iter().all(|&v| v >= 3) {
+                for &v in verts {
+                    let p = triangulation.points[v as usize];
+                    triples.push(Float4::new(p[0], p[1], 0.0, 0.0));
+                }
+            }
+        }
+
+        Ok(TriangleMesh {
+            context,
+            data: context.create_buffer_from(&triples, true)?,
+        })
+    }
+
+    /// Write the mesh to a Wavefront OBJ stream as `v`/`f` records. Points are written with their
+    /// existing duplication (three `v` records per triangle); no vertex deduplication is done.
+    pub fn to_obj_writer<W: Write>(&self, mut writer: W) -> Result<(), ClgeomError> {
+        let triangles = self.triangles()?;
+        let write_err = |e: std::io::Error| ClgeomError::new(&format!("Error writing OBJ stream: {}", e));
+
+        for triangle in &triangles {
+            for vertex in triangle {
+                writeln!(writer, "v {} {} {}", vertex[0], vertex[1], vertex[2]).map_err(write_err)?;
+            }
+        }
+        for i in 0..triangles.len() {
+            let base = i * 3 + 1;
+            writeln!(writer, "f {} {} {}", base, base + 1, base + 2).map_err(write_err)?;
+        }
+        Ok(())
+    }
+
     /// Return a vector of point triples representing the mesh
     pub fn triangles(&self) -> Result<Vec<[[f32; 3]; 3]>, ClgeomError> {
         let buffer_content = self.context.read_buffer(&self.data)?;
@@ -60,17 +151,654 @@ impl<'a> TriangleMesh<'a> {
     /// 
     pub fn scale(&self, multiplier: Float4) -> Result<(), ClgeomError> {
         let arg = vec![ParamType::Value(&multiplier)];
-        self.context.execute_kernel("scale", &self.data, arg)
+        self.context
+            .execute_kernel("scale", &self.data, arg, self.data.len())
     }
 
     /// Translate the mesh
-    /// 
+    ///
     /// * `offest` - the movement vector
-    /// 
+    ///
     pub fn translate(&self, offset: Float4) -> Result<(), ClgeomError>{
         let arg = vec![ParamType::Value(&offset)];
-        self.context.execute_kernel("translate", &self.data, arg)
+        self.context
+            .execute_kernel("translate", &self.data, arg, self.data.len())
+    }
+
+    /// Apply an arbitrary affine transform `M * [x, y, z, 1]` to every point, where `matrix` is
+    /// the 4 row vectors of `M` in row-major order. Unlike `scale`/`translate`, this lets
+    /// rotations and other non-axis-aligned motions run in a single GPU pass.
+    pub fn transform(&self, matrix: [Float4; 4]) -> Result<(), ClgeomError> {
+        let args = vec![
+            ParamType::Value(&matrix[0]),
+            ParamType::Value(&matrix[1]),
+            ParamType::Value(&matrix[2]),
+            ParamType::Value(&matrix[3]),
+        ];
+        self.context.execute_kernel("transform", &self.data, args, self.data.len())
+    }
+
+    /// Rotate the mesh by `angle` radians around `axis` (need not be normalized), via
+    /// Rodrigues' rotation formula. Fails if `axis` has zero length, since `normalize` can't turn
+    /// it into a direction and building the rotation matrix from it would silently produce a
+    /// uniform `cos(angle)` scale instead of a rotation.
+    pub fn rotate(&self, axis: [f32; 3], angle: f32) -> Result<(), ClgeomError> {
+        if axis == [0.0, 0.0, 0.0] {
+            return Err(ClgeomError::new("Rotation axis must not be the zero vector"));
+        }
+        self.transform(rotation_matrix(axis, angle))
+    }
+
+    /// Compact this mesh into an `IndexedTriangleMesh`, merging points whose coordinates are
+    /// equal once rounded to `precision` decimal places into a single shared vertex.
+    pub fn dedup(&self, precision: i32) -> Result<IndexedTriangleMesh<'a>, ClgeomError> {
+        let triangles = self.triangles()?;
+        let (vertices, indices) = dedup_triangles(&triangles, precision);
+        IndexedTriangleMesh::from_indexed(self.context, &vertices, &indices)
+    }
+
+    /// Axis-aligned bounding box of the mesh as `(min, max)` corners, computed as a parallel
+    /// min/max reduction of per-triangle bounds on the device; the host only ever reads back the
+    /// 2 final corners, never the full per-triangle buffer.
+    pub fn bounding_box(&self) -> Result<(Float4, Float4), ClgeomError> {
+        let triangle_count = self.triangle_count()?;
+        let bounds_min: Buffer<Float4> = self.context.create_empty_buffer(triangle_count)?;
+        let bounds_max: Buffer<Float4> = self.context.create_empty_buffer(triangle_count)?;
+        self.context.execute_kernel(
+            "triangle_bounds",
+            &self.data,
+            vec![ParamType::Buffer(&bounds_min), ParamType::Buffer(&bounds_max)],
+            triangle_count,
+        )?;
+        let min = reduce_to_scalar(self.context, "reduce_min", &bounds_min, triangle_count)?;
+        let max = reduce_to_scalar(self.context, "reduce_max", &bounds_max, triangle_count)?;
+        Ok((min, max))
+    }
+
+    /// Total surface area of the mesh, as a parallel sum reduction of each triangle's
+    /// `0.5 * length(cross(v1 - v0, v2 - v0))` on the device.
+    pub fn surface_area(&self) -> Result<f32, ClgeomError> {
+        let triangle_count = self.triangle_count()?;
+        let areas: Buffer<Float4> = self.context.create_empty_buffer(triangle_count)?;
+        self.context.execute_kernel(
+            "triangle_areas",
+            &self.data,
+            vec![ParamType::Buffer(&areas)],
+            triangle_count,
+        )?;
+        let total = reduce_to_scalar(self.context, "reduce_sum", &areas, triangle_count)?;
+        Ok(*total.get(0).unwrap_or(&0.0))
+    }
+
+    /// Signed volume enclosed by the mesh, as a parallel sum reduction of each triangle's
+    /// `dot(v0, cross(v1, v2)) / 6` on the device. Only meaningful for a closed, consistently
+    /// wound mesh.
+    pub fn volume(&self) -> Result<f32, ClgeomError> {
+        let triangle_count = self.triangle_count()?;
+        let volumes: Buffer<Float4> = self.context.create_empty_buffer(triangle_count)?;
+        self.context.execute_kernel(
+            "triangle_volumes",
+            &self.data,
+            vec![ParamType::Buffer(&volumes)],
+            triangle_count,
+        )?;
+        let total = reduce_to_scalar(self.context, "reduce_sum", &volumes, triangle_count)?;
+        Ok(*total.get(0).unwrap_or(&0.0))
+    }
+
+    /// Area-weighted centroid of the mesh: each triangle's centroid is weighted by its area
+    /// before being summed, which (unlike an unweighted vertex average) doesn't bias the result
+    /// towards densely-subdivided regions. Computed as a single parallel sum reduction over
+    /// `(centroid * area, area)` per triangle on the device.
+    pub fn centroid(&self) -> Result<Float4, ClgeomError> {
+        let triangle_count = self.triangle_count()?;
+        let weighted: Buffer<Float4> = self.context.create_empty_buffer(triangle_count)?;
+        self.context.execute_kernel(
+            "triangle_weighted_centroids",
+            &self.data,
+            vec![ParamType::Buffer(&weighted)],
+            triangle_count,
+        )?;
+        let total = reduce_to_scalar(self.context, "reduce_sum", &weighted, triangle_count)?;
+        let total_area = *total.get(3).unwrap_or(&0.0);
+        if total_area == 0.0 {
+            return Err(ClgeomError::new("Cannot compute centroid of a mesh with zero area"));
+        }
+        Ok(Float4::new(
+            *total.get(0).unwrap_or(&0.0) / total_area,
+            *total.get(1).unwrap_or(&0.0) / total_area,
+            *total.get(2).unwrap_or(&0.0) / total_area,
+            0.0,
+        ))
+    }
+
+    // The number of triangles backing this mesh, i.e. the work size for per-triangle kernels.
+    fn triangle_count(&self) -> Result<usize, ClgeomError> {
+        if (self.data.len() % 3) != 0 {
+            return Err(ClgeomError::new("Buffer length is not a multiple of 3."));
+        }
+        let triangle_count = self.data.len() / 3;
+        if triangle_count == 0 {
+            return Err(ClgeomError::new("Mesh has no triangles"));
+        }
+        Ok(triangle_count)
+    }
+
+    /// Build a bounding volume hierarchy over this mesh's triangles, by recursively
+    /// median-splitting triangle centroids on the longest axis of their bounds, for use with
+    /// `intersect`.
+    pub fn build_bvh(&self) -> Result<Bvh, ClgeomError> {
+        let triangles = self.triangles()?;
+        let (nodes, prim_order) = build_bvh_nodes(&triangles);
+        let flat_nodes = flatten_bvh(&nodes);
+        let prim_indices: Vec<Float4> = prim_order
+            .iter()
+            .map(|&index| Float4::new(index as f32, 0.0, 0.0, 0.0))
+            .collect();
+
+        Ok(Bvh {
+            nodes: self.context.create_buffer_from(&flat_nodes, true)?,
+            prim_indices: self.context.create_buffer_from(&prim_indices, true)?,
+        })
+    }
+
+    /// Cast one ray per `(origin, direction)` pair against `bvh` and return the nearest hit on
+    /// this mesh's surface, if any. Traversal walks the BVH with an explicit stack, testing each
+    /// leaf's triangles with the Möller–Trumbore algorithm.
+    pub fn intersect(
+        &self,
+        bvh: &Bvh,
+        origins: &[Float4],
+        directions: &[Float4],
+    ) -> Result<Vec<Option<Hit>>, ClgeomError> {
+        if origins.len() != directions.len() {
+            return Err(ClgeomError::new("origins and directions must have the same length"));
+        }
+        let ray_count = origins.len();
+        let origins_buf = self.context.create_buffer_from(origins, false)?;
+        let directions_buf = self.context.create_buffer_from(directions, false)?;
+        let output: Buffer<Float4> = self.context.create_empty_buffer(ray_count)?;
+
+        self.context.execute_kernel(
+            "raycast",
+            &origins_buf,
+            vec![
+                ParamType::Buffer(&directions_buf),
+                ParamType::Buffer(&bvh.nodes),
+                ParamType::Buffer(&bvh.prim_indices),
+                ParamType::Buffer(&self.data),
+                ParamType::Buffer(&output),
+            ],
+            ray_count,
+        )?;
+
+        let raw_hits = self.context.read_buffer(&output)?;
+        Ok(raw_hits.iter().map(hit_from_float4).collect())
+    }
+
+    /// Compute a unit normal for each triangle via `normalize(cross(v1 - v0, v2 - v0))`, run on
+    /// the device.
+    pub fn triangle_normals(&self) -> Result<Buffer<Float4>, ClgeomError> {
+        let triangle_count = self.data.len() / 3;
+        let output: Buffer<Float4> = self.context.create_empty_buffer(triangle_count)?;
+        self.context.execute_kernel(
+            "normals",
+            &self.data,
+            vec![ParamType::Buffer(&output)],
+            triangle_count,
+        )?;
+        Ok(output)
+    }
+
+    /// Compute an area-weighted vertex normal for each point in the mesh. Points whose
+    /// coordinates coincide (to `precision` decimal places, as in `dedup`) accumulate the
+    /// un-normalized face normal of every triangle they belong to before the sum is normalized,
+    /// so larger adjacent triangles contribute proportionally more to the shared normal.
+    pub fn vertex_normals(&self, precision: i32) -> Result<Vec<[f32; 3]>, ClgeomError> {
+        Ok(vertex_normals_from_triangles(&self.triangles()?, precision))
+    }
+}
+
+/// A mesh of triangles stored as a unique vertex buffer plus a triangle index buffer (three
+/// indices per triangle), avoiding the per-triangle vertex duplication of `TriangleMesh`.
+pub struct IndexedTriangleMesh<'a> {
+    context: &'a ComputeContext,
+    vertices: Buffer<Float4>,
+    indices: Buffer<u32>,
+}
+
+impl<'a> IndexedTriangleMesh<'a> {
+    /// Build an `IndexedTriangleMesh` from a unique vertex list and a flat triangle index buffer
+    /// (three indices per triangle).
+    pub fn from_indexed(
+        context: &'a ComputeContext,
+        vertices: &[Float4],
+        indices: &[u32],
+    ) -> Result<IndexedTriangleMesh<'a>, ClgeomError> {
+        if (indices.len() % 3) != 0 {
+            return Err(ClgeomError::new("Index buffer length is not a multiple of 3."));
+        }
+        Ok(IndexedTriangleMesh {
+            context,
+            vertices: context.create_buffer_from(vertices, true)?,
+            indices: context.create_buffer_from(indices, true)?,
+        })
+    }
+
+    /// Reconstruct the flat point-triple layout by resolving each triangle's indices against the
+    /// vertex buffer.
+    pub fn triangles(&self) -> Result<Vec<[[f32; 3]; 3]>, ClgeomError> {
+        let vertices = self.context.read_buffer(&self.vertices)?;
+        let indices = self.context.read_buffer_generic(&self.indices)?;
+        if (indices.len() % 3) != 0 {
+            return Err(ClgeomError::new("Index buffer length is not a multiple of 3."));
+        }
+        indices
+            .chunks(3)
+            .map(|triangle_indices| {
+                let mut result = [[0.0f32; 3]; 3];
+                for (i, &index) in triangle_indices.iter().enumerate() {
+                    let vertex = vertices
+                        .get(index as usize)
+                        .ok_or_else(|| ClgeomError::new("Index buffer references out-of-range vertex"))?;
+                    result[i] = TriangleMesh::get_coords(vertex);
+                }
+                Ok(result)
+            })
+            .collect()
+    }
+}
+
+/// A bounding volume hierarchy over a `TriangleMesh`'s triangles, built by `build_bvh` and
+/// consumed by `intersect`.
+pub struct Bvh {
+    nodes: Buffer<Float4>,
+    prim_indices: Buffer<Float4>,
+}
+
+/// The nearest point where a ray crossed a mesh's surface, as returned by `intersect`.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    /// Index of the triangle hit, as returned by `TriangleMesh::triangles`.
+    pub triangle_index: usize,
+    /// Distance from the ray origin to the hit, in units of the ray direction's length.
+    pub t: f32,
+    /// Barycentric coordinate of the hit along the triangle's first edge.
+    pub u: f32,
+    /// Barycentric coordinate of the hit along the triangle's second edge.
+    pub v: f32,
+}
+
+// Maximum triangles per BVH leaf before it is split further.
+const BVH_LEAF_SIZE: usize = 4;
+
+// A node in the flattened host-side BVH, prior to upload.
+struct BvhNode {
+    bounds_min: [f32; 3],
+    bounds_max: [f32; 3],
+    left: i32,
+    right: i32,
+    first_prim: u32,
+    prim_count: u32,
+}
+
+// Build a BVH over `triangles`' centroids, returning its nodes (root first) and a permutation of
+// triangle indices such that each leaf's triangles occupy a contiguous `[first_prim, first_prim +
+// prim_count)` range of the permutation.
+fn build_bvh_nodes(triangles: &[[[f32; 3]; 3]]) -> (Vec<BvhNode>, Vec<u32>) {
+    let centroids: Vec<[f32; 3]> = triangles
+        .iter()
+        .map(|t| {
+            [
+                (t[0][0] + t[1][0] + t[2][0]) / 3.0,
+                (t[0][1] + t[1][1] + t[2][1]) / 3.0,
+                (t[0][2] + t[1][2] + t[2][2]) / 3.0,
+            ]
+        })
+        .collect();
+    let bounds: Vec<([f32; 3], [f32; 3])> = triangles
+        .iter()
+        .map(|t| {
+            let mut lo = t[0];
+            let mut hi = t[0];
+            for point in &t[1..] {
+                lo = [lo[0].min(point[0]), lo[1].min(point[1]), lo[2].min(point[2])];
+                hi = [hi[0].max(point[0]), hi[1].max(point[1]), hi[2].max(point[2])];
+            }
+            (lo, hi)
+        })
+        .collect();
+
+    let mut order: Vec<u32> = (0..triangles.len() as u32).collect();
+    let order_len = order.len();
+    let mut nodes: Vec<BvhNode> = Vec::new();
+    build_bvh_range(&mut nodes, &mut order, 0, order_len, &centroids, &bounds);
+    (nodes, order)
+}
+
+// Recursively build the node covering `order[start..end]`, splitting on the longest axis of the
+// range's bounds (the union of its triangles' AABBs), and return its index in `nodes`.
+fn build_bvh_range(
+    nodes: &mut Vec<BvhNode>,
+    order: &mut [u32],
+    start: usize,
+    end: usize,
+    centroids: &[[f32; 3]],
+    bounds: &[([f32; 3], [f32; 3])],
+) -> usize {
+    let (bounds_min, bounds_max) = range_bounds(&order[start..end], bounds);
+    let node_index = nodes.len();
+    nodes.push(BvhNode {
+        bounds_min,
+        bounds_max,
+        left: -1,
+        right: -1,
+        first_prim: start as u32,
+        prim_count: (end - start) as u32,
+    });
+
+    if end - start <= BVH_LEAF_SIZE {
+        return node_index;
+    }
+
+    let extent = sub(&bounds_max, &bounds_min);
+    let axis = if extent[0] >= extent[1] && extent[0] >= extent[2] {
+        0
+    } else if extent[1] >= extent[2] {
+        1
+    } else {
+        2
+    };
+
+    order[start..end].sort_by(|&a, &b| {
+        centroids[a as usize][axis]
+            .partial_cmp(&centroids[b as usize][axis])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let mid = start + (end - start) / 2;
+
+    let left = build_bvh_range(nodes, order, start, mid, centroids, bounds);
+    let right = build_bvh_range(nodes, order, mid, end, centroids, bounds);
+
+    let node = &mut nodes[node_index];
+    node.left = left as i32;
+    node.right = right as i32;
+    node.prim_count = 0;
+    node_index
+}
+
+// The union of the AABBs of the triangles referenced by `indices`.
+fn range_bounds(indices: &[u32], bounds: &[([f32; 3], [f32; 3])]) -> ([f32; 3], [f32; 3]) {
+    let mut lo = bounds[indices[0] as usize].0;
+    let mut hi = bounds[indices[0] as usize].1;
+    for &index in &indices[1..] {
+        let (blo, bhi) = bounds[index as usize];
+        lo = [lo[0].min(blo[0]), lo[1].min(blo[1]), lo[2].min(blo[2])];
+        hi = [hi[0].max(bhi[0]), hi[1].max(bhi[1]), hi[2].max(bhi[2])];
+    }
+    (lo, hi)
+}
+
+// Flatten BVH nodes into the 3-`Float4`-per-node layout the `raycast` kernel expects: bounds min,
+// bounds max, then (left, right, first_prim, prim_count) packed into a `Float4`.
+fn flatten_bvh(nodes: &[BvhNode]) -> Vec<Float4> {
+    let mut flat = Vec::with_capacity(nodes.len() * 3);
+    for node in nodes {
+        flat.push(Float4::new(node.bounds_min[0], node.bounds_min[1], node.bounds_min[2], 0.0));
+        flat.push(Float4::new(node.bounds_max[0], node.bounds_max[1], node.bounds_max[2], 0.0));
+        flat.push(Float4::new(
+            node.left as f32,
+            node.right as f32,
+            node.first_prim as f32,
+            node.prim_count as f32,
+        ));
+    }
+    flat
+}
+
+// Repeatedly halve `buffer`'s live element count by running `kernel_name` (one of the
+// `reduce_*` kernels) until a single element remains, then read back only that element — the
+// reduction itself never transfers the full buffer to the host.
+fn reduce_to_scalar(
+    context: &ComputeContext,
+    kernel_name: &str,
+    buffer: &Buffer<Float4>,
+    count: usize,
+) -> Result<Float4, ClgeomError> {
+    let mut remaining = count;
+    for half in reduction_sizes(count) {
+        let params = Float4::new(remaining as f32, half as f32, 0.0, 0.0);
+        context.execute_kernel(kernel_name, buffer, vec![ParamType::Value(&params)], half)?;
+        remaining = half;
+    }
+    context.read_buffer_element(buffer, 0)
+}
+
+// The sequence of live-element counts `reduce_to_scalar` halves a `count`-element buffer down
+// through to reach a single element, rounding each odd count up so its lone unpaired element
+// carries over instead of being dropped (e.g. 5 -> 3 -> 2 -> 1).
+fn reduction_sizes(count: usize) -> Vec<usize> {
+    let mut sizes = Vec::new();
+    let mut remaining = count;
+    while remaining > 1 {
+        remaining = (remaining + 1) / 2;
+        sizes.push(remaining);
+    }
+    sizes
+}
+
+// Decode a `raycast` kernel output entry `(triangle_index, t, u, v)` into a `Hit`, treating a
+// negative triangle index (no candidate triangle found) as a miss.
+fn hit_from_float4(raw: &Float4) -> Option<Hit> {
+    let triangle_index = *raw.get(0)?;
+    if triangle_index < 0.0 {
+        return None;
     }
+    Some(Hit {
+        triangle_index: triangle_index as usize,
+        t: *raw.get(1)?,
+        u: *raw.get(2)?,
+        v: *raw.get(3)?,
+    })
+}
+
+// Incremental Delaunay triangulation of a 2D point set, built by inserting one point at a time
+// and restoring the empty-circumcircle property by flipping edges (Lawson's algorithm).
+//
+// Triangles are stored CCW as `[u32; 3]` vertex indices into `points`; a removed triangle's slot
+// becomes `None` rather than being compacted, so other triangles' indices stay valid.
+// `adjacency` maps each directed edge `(u, v)` to the id of the triangle whose CCW boundary
+// contains that edge, letting the triangle across an edge be found via the reversed edge `(v, u)`.
+struct Triangulation {
+    points: Vec<[f32; 2]>,
+    triangles: Vec<Option<[u32; 3]>>,
+    adjacency: HashMap<(u32, u32), usize>,
+    poisoned: bool,
+    // Threshold below which an `orient2d` result (units of length^2) is treated as "on the line"
+    // rather than a genuine degeneracy. Scaled to the input's span since `orient2d` is computed
+    // from `f32` coordinates (~1e-7 relative precision): `f64::EPSILON` is tiny enough that only
+    // exact bit-for-bit collinearity would trip it, letting near-degenerate input through to
+    // produce sliver triangles instead of poisoning.
+    degenerate_epsilon: f64,
+}
+
+impl Triangulation {
+    // Triangulate `points` via incremental Delaunay insertion with edge flipping, starting from a
+    // super-triangle large enough to enclose every input point. Returns `None` if the input is
+    // degenerate (e.g. collinear): insertion poisons the triangulation, or no triangle free of the
+    // super-triangle's corners remains. The first 3 points in
+    // the result are always the discarded super-triangle's corners.
+    fn build(points: &[[f32; 2]]) -> Option<Triangulation> {
+        let (min, max) = points.iter().skip(1).fold((points[0], points[0]), |(min, max), p| {
+            (
+                [min[0].min(p[0]), min[1].min(p[1])],
+                [max[0].max(p[0]), max[1].max(p[1])],
+            )
+        });
+        let span = (max[0] - min[0]).max(max[1] - min[1]).max(1.0);
+        let mid = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0];
+
+        let mut triangulation = Triangulation {
+            points: vec![
+                [mid[0] - 20.0 * span, mid[1] - span],
+                [mid[0] + 20.0 * span, mid[1] - span],
+                [mid[0], mid[1] + 20.0 * span],
+            ],
+            triangles: Vec::new(),
+            adjacency: HashMap::new(),
+            poisoned: false,
+            degenerate_epsilon: f64::from(span) * f64::from(span) * f64::from(f32::EPSILON),
+        };
+        triangulation.add_triangle([0, 1, 2]);
+
+        for &point in points {
+            triangulation.insert_point(point);
+        }
+
+        // Collinear input can be inserted without ever landing on an edge, yet leaves every
+        // triangle touching the super-triangle; treat that as degenerate too.
+        let has_real_triangle = triangulation
+            .triangles
+            .iter()
+            .flatten()
+            .any(|verts| verts.iter().all(|&v| v >= 3));
+        if triangulation.poisoned || !has_real_triangle {
+            None
+        } else {
+            Some(triangulation)
+        }
+    }
+
+    // Register a new triangle and its 3 directed edges, returning its id.
+    fn add_triangle(&mut self, verts: [u32; 3]) -> usize {
+        let id = self.triangles.len();
+        self.triangles.push(Some(verts));
+        for edge in tri_edges(verts) {
+            self.adjacency.insert(edge, id);
+        }
+        id
+    }
+
+    // Unregister a triangle and its directed edges, leaving a tombstone behind.
+    fn remove_triangle(&mut self, id: usize) {
+        if let Some(verts) = self.triangles[id].take() {
+            for edge in tri_edges(verts) {
+                self.adjacency.remove(&edge);
+            }
+        }
+    }
+
+    fn point(&self, index: u32) -> [f32; 2] {
+        self.points[index as usize]
+    }
+
+    // Find the (still-live) triangle whose CCW boundary encloses `p`.
+    fn locate(&self, p: [f32; 2]) -> Option<usize> {
+        self.triangles.iter().enumerate().find_map(|(id, verts)| {
+            let [a, b, c] = (*verts)?;
+            let inside = [
+                orient2d(self.point(a), self.point(b), p),
+                orient2d(self.point(b), self.point(c), p),
+                orient2d(self.point(c), self.point(a), p),
+            ];
+            if inside.iter().all(|&side| side >= 0.0) {
+                Some(id)
+            } else {
+                None
+            }
+        })
+    }
+
+    // Insert `p`, splitting the triangle that contains it into three and legalizing the edges
+    // opposite the new point. Sets `poisoned` instead of panicking if `p` can't be located inside
+    // any triangle, or lands on (within `degenerate_epsilon` of) the line of one of its edges.
+    fn insert_point(&mut self, p: [f32; 2]) {
+        if self.poisoned {
+            return;
+        }
+        let Some(containing) = self.locate(p) else {
+            self.poisoned = true;
+            return;
+        };
+        let [a, b, c] = self.triangles[containing].expect("locate only returns live triangles");
+        let areas = [
+            orient2d(self.point(a), self.point(b), p),
+            orient2d(self.point(b), self.point(c), p),
+            orient2d(self.point(c), self.point(a), p),
+        ];
+        if areas.iter().any(|side| side.abs() < self.degenerate_epsilon) {
+            self.poisoned = true;
+            return;
+        }
+
+        let new_index = self.points.len() as u32;
+        self.points.push(p);
+
+        self.remove_triangle(containing);
+        self.add_triangle([a, b, new_index]);
+        self.add_triangle([b, c, new_index]);
+        self.add_triangle([c, a, new_index]);
+
+        let mut stack = vec![(a, b), (b, c), (c, a)];
+        while let Some((u, v)) = stack.pop() {
+            self.legalize(u, v, new_index, &mut stack);
+        }
+    }
+
+    // If the triangle across edge `(u, v)` from the one containing `d` violates the Delaunay
+    // condition (its opposite vertex lies inside that triangle's circumcircle), flip the shared
+    // edge to `(d, w)` and push the two newly exposed edges for re-checking.
+    fn legalize(&mut self, u: u32, v: u32, d: u32, stack: &mut Vec<(u32, u32)>) {
+        let Some(&opp_id) = self.adjacency.get(&(v, u)) else {
+            return;
+        };
+        let opp_verts = self.triangles[opp_id].expect("adjacency only references live triangles");
+        let Some(w) = third_vertex(opp_verts, u, v) else {
+            return;
+        };
+
+        if in_circumcircle(self.point(u), self.point(v), self.point(d), self.point(w)) {
+            let Some(&this_id) = self.adjacency.get(&(u, v)) else {
+                return;
+            };
+            self.remove_triangle(this_id);
+            self.remove_triangle(opp_id);
+            self.add_triangle([v, d, w]);
+            self.add_triangle([d, u, w]);
+            stack.push((u, w));
+            stack.push((w, v));
+        }
+    }
+}
+
+// The 3 CCW directed edges of a triangle.
+fn tri_edges(verts: [u32; 3]) -> [(u32, u32); 3] {
+    [(verts[0], verts[1]), (verts[1], verts[2]), (verts[2], verts[0])]
+}
+
+// The vertex of `verts` that is neither `u` nor `v`.
+fn third_vertex(verts: [u32; 3], u: u32, v: u32) -> Option<u32> {
+    verts.into_iter().find(|&x| x != u && x != v)
+}
+
+// Sign of the 2x2 determinant of (b - a) and (c - a): positive when a, b, c turn counterclockwise,
+// i.e. c is to the left of the directed line a->b.
+fn orient2d(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f64 {
+    let (ax, ay) = (f64::from(a[0]), f64::from(a[1]));
+    let (bx, by) = (f64::from(b[0]), f64::from(b[1]));
+    let (cx, cy) = (f64::from(c[0]), f64::from(c[1]));
+    (bx - ax) * (cy - ay) - (by - ay) * (cx - ax)
+}
+
+// True if `d` lies inside the circumcircle of CCW triangle `(a, b, c)`, via the standard lifted
+// 3x3 determinant test.
+fn in_circumcircle(a: [f32; 2], b: [f32; 2], c: [f32; 2], d: [f32; 2]) -> bool {
+    let (ax, ay) = (f64::from(a[0]) - f64::from(d[0]), f64::from(a[1]) - f64::from(d[1]));
+    let (bx, by) = (f64::from(b[0]) - f64::from(d[0]), f64::from(b[1]) - f64::from(d[1]));
+    let (cx, cy) = (f64::from(c[0]) - f64::from(d[0]), f64::from(c[1]) - f64::from(d[1]));
+    let det = (ax * ax + ay * ay) * (bx * cy - by * cx) - (bx * bx + by * by) * (ax * cy - ay * cx)
+        + (cx * cx + cy * cy) * (ax * by - ay * bx);
+    det > 0.0
 }
 
 // Helps convert array of points to vector of point triples
@@ -101,3 +829,289 @@ impl TriangleAccumulator {
         self
     }
 }
+
+// Merge `triangles`' points whose coordinates are equal once rounded to `precision` decimal
+// places into a single shared vertex, returning the deduplicated vertex list and the flat
+// triangle index buffer (three indices per input triangle) referencing it.
+fn dedup_triangles(triangles: &[[[f32; 3]; 3]], precision: i32) -> (Vec<Float4>, Vec<u32>) {
+    let scale = 10f32.powi(precision);
+    let mut lookup: HashMap<[i64; 3], u32> = HashMap::new();
+    let mut vertices: Vec<Float4> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for triangle in triangles {
+        for point in triangle {
+            let key = quantize(point, scale);
+            let index = *lookup.entry(key).or_insert_with(|| {
+                vertices.push(Float4::new(point[0], point[1], point[2], 0.0));
+                (vertices.len() - 1) as u32
+            });
+            indices.push(index);
+        }
+    }
+
+    (vertices, indices)
+}
+
+// Compute an area-weighted vertex normal for each point in `triangles`. Points whose coordinates
+// coincide (to `precision` decimal places, as in `dedup_triangles`) accumulate the un-normalized
+// face normal of every triangle they belong to before the sum is normalized, so larger adjacent
+// triangles contribute proportionally more to the shared normal. Returns one normal per input
+// point, in the same `triangles`-flattened order.
+fn vertex_normals_from_triangles(triangles: &[[[f32; 3]; 3]], precision: i32) -> Vec<[f32; 3]> {
+    let scale = 10f32.powi(precision);
+    let mut lookup: HashMap<[i64; 3], usize> = HashMap::new();
+    let mut keys: Vec<[i64; 3]> = Vec::new();
+    let mut accum: Vec<[f32; 3]> = Vec::new();
+
+    let mut point_keys: Vec<[i64; 3]> = Vec::with_capacity(triangles.len() * 3);
+    for triangle in triangles {
+        let face_normal = cross(&sub(&triangle[1], &triangle[0]), &sub(&triangle[2], &triangle[0]));
+        for point in triangle {
+            let key = quantize(point, scale);
+            let index = *lookup.entry(key).or_insert_with(|| {
+                keys.push(key);
+                accum.push([0.0; 3]);
+                accum.len() - 1
+            });
+            accum[index] = add(&accum[index], &face_normal);
+            point_keys.push(key);
+        }
+    }
+
+    let normalized: HashMap<[i64; 3], [f32; 3]> = keys
+        .into_iter()
+        .zip(accum.into_iter().map(|n| normalize(&n)))
+        .collect();
+
+    point_keys.iter().map(|key| normalized[key]).collect()
+}
+
+// Round a point's coordinates to integer keys after scaling, for use as a `HashMap` key that
+// treats near-equal coordinates as the same vertex
+fn quantize(point: &[f32; 3], scale: f32) -> [i64; 3] {
+    [
+        (point[0] * scale).round() as i64,
+        (point[1] * scale).round() as i64,
+        (point[2] * scale).round() as i64,
+    ]
+}
+
+// Subtract two vectors
+fn sub(a: &[f32; 3], b: &[f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+// Add two vectors
+fn add(a: &[f32; 3], b: &[f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+// Cross product of two vectors
+fn cross(a: &[f32; 3], b: &[f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+// Normalize a vector, leaving degenerate (zero-length) vectors unchanged
+fn normalize(v: &[f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len == 0.0 {
+        *v
+    } else {
+        [v[0] / len, v[1] / len, v[2] / len]
+    }
+}
+
+// Build the `M * [x, y, z, 1]` affine transform matrix (as `transform`'s 4 row vectors) for a
+// rotation by `angle` radians around `axis`, via Rodrigues' rotation formula. `axis` need not be
+// normalized, but must not be the zero vector.
+fn rotation_matrix(axis: [f32; 3], angle: f32) -> [Float4; 4] {
+    let [x, y, z] = normalize(&axis);
+    let (sin, cos) = angle.sin_cos();
+    let one_minus_cos = 1.0 - cos;
+
+    [
+        Float4::new(
+            cos + x * x * one_minus_cos,
+            x * y * one_minus_cos - z * sin,
+            x * z * one_minus_cos + y * sin,
+            0.0,
+        ),
+        Float4::new(
+            y * x * one_minus_cos + z * sin,
+            cos + y * y * one_minus_cos,
+            y * z * one_minus_cos - x * sin,
+            0.0,
+        ),
+        Float4::new(
+            z * x * one_minus_cos - y * sin,
+            z * y * one_minus_cos + x * sin,
+            cos + z * z * one_minus_cos,
+            0.0,
+        ),
+        Float4::new(0.0, 0.0, 0.0, 1.0),
+    ]
+}
+
+// Parse an OBJ "v x y z" record's coordinates into a `Float4`, ignoring any trailing `w`.
+fn parse_obj_vertex<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Float4, ClgeomError> {
+    let mut parse_next = || -> Result<f32, ClgeomError> {
+        let token = tokens
+            .next()
+            .ok_or_else(|| ClgeomError::new("OBJ 'v' record must have 3 coordinates"))?;
+        token
+            .parse()
+            .map_err(|e| ClgeomError::new(&format!("Invalid OBJ vertex coordinate '{}': {}", token, e)))
+    };
+    let x = parse_next()?;
+    let y = parse_next()?;
+    let z = parse_next()?;
+    Ok(Float4::new(x, y, z, 0.0))
+}
+
+// Parse a single OBJ face vertex reference ("v", "v/vt", "v/vt/vn" or "v//vn") into a 0-based
+// index into the vertex list seen so far. Negative indices are relative to `vertex_count`, per
+// the OBJ spec.
+fn parse_obj_face_index(token: &str, vertex_count: usize) -> Result<usize, ClgeomError> {
+    let v_str = token.split('/').next().unwrap_or(token);
+    let v: i64 = v_str
+        .parse()
+        .map_err(|e| ClgeomError::new(&format!("Invalid OBJ face index '{}': {}", token, e)))?;
+    if v > 0 {
+        Ok((v - 1) as usize)
+    } else if v < 0 {
+        vertex_count
+            .checked_sub((-v) as usize)
+            .ok_or_else(|| ClgeomError::new(&format!("OBJ face index '{}' out of range", token)))
+    } else {
+        Err(ClgeomError::new("OBJ face index must not be 0"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup_triangles_merges_shared_points_within_precision() {
+        // Two triangles sharing an edge, with the shared points differing only in the 6th
+        // decimal place.
+        let triangles = [
+            [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.000_003, 0.0]],
+            [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+        ];
+        let (vertices, indices) = dedup_triangles(&triangles, 2);
+        assert_eq!(vertices.len(), 4, "5 distinct input points should collapse to 4 unique vertices");
+        assert_eq!(indices.len(), 6);
+        assert_eq!(indices[0], indices[3], "both triangles' [0,0,0] corner should share an index");
+    }
+
+    #[test]
+    fn vertex_normals_from_triangles_points_away_from_single_flat_triangle() {
+        let triangles = [[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]];
+        let normals = vertex_normals_from_triangles(&triangles, 2);
+        assert_eq!(normals.len(), 3);
+        for normal in normals {
+            assert!((normal[2] - 1.0).abs() < 1e-6, "flat XY triangle should have a +Z normal");
+        }
+    }
+
+    #[test]
+    fn reduction_sizes_carries_over_the_odd_element_each_step() {
+        assert_eq!(reduction_sizes(5), vec![3, 2, 1]);
+        assert_eq!(reduction_sizes(8), vec![4, 2, 1]);
+        assert_eq!(reduction_sizes(1), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn rotation_matrix_quarter_turn_about_z_maps_x_to_y() {
+        let matrix = rotation_matrix([0.0, 0.0, 1.0], std::f32::consts::FRAC_PI_2);
+        // Applying row i to the +X axis (1, 0, 0, 1) just picks out that row's first component.
+        let rotated_x = [*matrix[0].get(0).unwrap(), *matrix[1].get(0).unwrap(), *matrix[2].get(0).unwrap()];
+        assert!(
+            rotated_x[0].abs() < 1e-6 && (rotated_x[1] - 1.0).abs() < 1e-6 && rotated_x[2].abs() < 1e-6,
+            "rotating +X by 90deg about Z should give +Y, got {:?}", rotated_x
+        );
+    }
+
+    #[test]
+    fn parse_obj_vertex_reads_xyz_and_ignores_trailing_w() {
+        let v = parse_obj_vertex(["1.0", "2.5", "-3.0", "1.0"].into_iter()).unwrap();
+        assert_eq!((*v.get(0).unwrap(), *v.get(1).unwrap(), *v.get(2).unwrap()), (1.0, 2.5, -3.0));
+    }
+
+    #[test]
+    fn parse_obj_vertex_rejects_missing_coordinate() {
+        assert!(parse_obj_vertex(["1.0", "2.5"].into_iter()).is_err());
+    }
+
+    #[test]
+    fn parse_obj_face_index_handles_one_based_and_negative_references() {
+        assert_eq!(parse_obj_face_index("3", 10).unwrap(), 2);
+        assert_eq!(parse_obj_face_index("3/4/5", 10).unwrap(), 2);
+        assert_eq!(parse_obj_face_index("-1", 10).unwrap(), 9);
+        assert!(parse_obj_face_index("0", 10).is_err());
+    }
+
+    // Real (non-super-triangle) triangles in a built `Triangulation`.
+    fn real_triangles(triangulation: &Triangulation) -> Vec<[u32; 3]> {
+        triangulation
+            .triangles
+            .iter()
+            .flatten()
+            .filter(|verts| verts.iter().all(|&v| v >= 3))
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn triangulation_build_splits_unit_square_into_two_triangles() {
+        let points = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        let triangulation = Triangulation::build(&points).expect("unit square is non-degenerate");
+        assert_eq!(real_triangles(&triangulation).len(), 2);
+    }
+
+    #[test]
+    fn triangulation_build_fails_on_collinear_points() {
+        let points = [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]];
+        assert!(Triangulation::build(&points).is_none());
+    }
+
+    // A unit right triangle placed at `offset`, distinct enough that each has its own AABB.
+    fn unit_triangle_at(offset: f32) -> [[f32; 3]; 3] {
+        [[offset, 0.0, 0.0], [offset + 1.0, 0.0, 0.0], [offset, 1.0, 0.0]]
+    }
+
+    #[test]
+    fn build_bvh_nodes_keeps_every_triangle_in_exactly_one_leaf() {
+        let triangles: Vec<[[f32; 3]; 3]> = (0..10).map(|i| unit_triangle_at(i as f32 * 10.0)).collect();
+        let (nodes, order) = build_bvh_nodes(&triangles);
+
+        assert_eq!(order.len(), triangles.len());
+        let mut seen: Vec<u32> = order.clone();
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen.len(), triangles.len(), "every triangle index should appear exactly once");
+
+        let leaf_prim_count: u32 = nodes
+            .iter()
+            .filter(|n| n.left < 0)
+            .map(|n| n.prim_count)
+            .sum();
+        assert_eq!(leaf_prim_count as usize, triangles.len());
+    }
+
+    #[test]
+    fn build_bvh_nodes_single_triangle_is_a_single_leaf() {
+        let triangles = [unit_triangle_at(0.0)];
+        let (nodes, order) = build_bvh_nodes(&triangles);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(order, vec![0]);
+        assert_eq!(nodes[0].first_prim, 0);
+        assert_eq!(nodes[0].prim_count, 1);
+    }
+}